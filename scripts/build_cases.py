@@ -1,12 +1,14 @@
 import csv
 import shutil
-from build_config import build_config
+import sys
+from build_config import build_config, output_path, template_seed, OUTPUT_PATH_TEMPLATE
 
-def build_case(overlay, committees, nodes, config_name, max_view=1, network='default'):
-    build_config(overlay, committees, nodes, config_name, max_view, network)
+def build_case(overlay, committees, nodes, config_name, max_view=1, network='default', output_template=OUTPUT_PATH_TEMPLATE):
+    build_config(overlay, committees, nodes, config_name, max_view, network, output_template)
     shutil.move(f"{config_name}.json", "../configs/")
 
-def build_cases(csv_path):
+def build_cases(csv_path, output_template=OUTPUT_PATH_TEMPLATE):
+    cases = []
     with open(csv_path, 'r') as csv_file:
         reader = csv.reader(csv_file)
         
@@ -18,17 +20,34 @@ def build_cases(csv_path):
             
             config_name = f"{overlay_type}_{node_count}_{committees}"
             
-            build_case(overlay_type, committees, node_count, f"{config_name}_view_1_default")
-            build_case(overlay_type, committees, node_count, f"{config_name}_view_10_default", max_view="10")
-            build_case(overlay_type, committees, node_count, f"{config_name}_view_10_optimistic", max_view="10", network="optimistic")
-            build_case(overlay_type, committees, node_count, f"{config_name}_view_10_pessimistic", max_view="10", network="pessimistic")
+            cases.append((overlay_type, committees, node_count, f"{config_name}_view_1_default", "1", "default"))
+            cases.append((overlay_type, committees, node_count, f"{config_name}_view_10_default", "10", "default"))
+            cases.append((overlay_type, committees, node_count, f"{config_name}_view_10_optimistic", "10", "optimistic"))
+            cases.append((overlay_type, committees, node_count, f"{config_name}_view_10_pessimistic", "10", "pessimistic"))
+
+    # Reject templates that would make several cases write to the same output before building anything
+    paths = {}
+    seed = template_seed()
+    for overlay_type, committees, node_count, config_name, max_view, network in cases:
+        path = output_path(output_template, overlay_type, committees, node_count, config_name, max_view, network, seed)
+        if path in paths:
+            print(f"Output template gives {paths[path]} and {config_name} the same output path {path}")
+            sys.exit(1)
+        paths[path] = config_name
+
+    for overlay_type, committees, node_count, config_name, max_view, network in cases:
+        build_case(overlay_type, committees, node_count, config_name, max_view, network, output_template)
 
 if __name__ == "__main__":
-    import sys
-    if len(sys.argv) != 2:
-        print("Usage: python generate_configs.py <path_to_csv_file>")
+    if len(sys.argv) not in (2, 3):
+        print("Usage: python generate_configs.py <path_to_csv_file> [output_template]")
         sys.exit(1)
     
     csv_path = sys.argv[1]
-    build_cases(csv_path)
+    output_template = sys.argv[2] if len(sys.argv) > 2 else OUTPUT_PATH_TEMPLATE
+    try:
+        build_cases(csv_path, output_template)
+    except ValueError as error:
+        print(error)
+        sys.exit(1)
 
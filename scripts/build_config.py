@@ -1,13 +1,34 @@
 import sys
 import os
 import json
+from datetime import date
 
 TEMPLATE_PATH = "config_builder/template.json"
 TEMPORARY_PATH = "config_builder/temp.json"
 NETWORK_UPDATE_PATH = "config_builder/network"
 RECORD_UPDATE_PATH = "config_builder/record.json"
+OUTPUT_PATH_TEMPLATE = "output/{config_name}.json"
 
-def build_config(overlay_type, number_of_committees, node_count, config_name, max_view=1, network='default'):
+def template_seed():
+    with open(TEMPLATE_PATH, 'r') as f:
+        return json.load(f)["seed"]
+
+def output_path(output_template, overlay_type, number_of_committees, node_count, config_name, max_view=1, network='default', seed=0):
+    try:
+        return output_template.format(
+            config_name=config_name,
+            date=date.today().isoformat(),
+            seed=seed,
+            overlay=overlay_type,
+            committees=number_of_committees,
+            nodes=node_count,
+            max_view=max_view,
+            network=network,
+        )
+    except (KeyError, IndexError, ValueError) as error:
+        raise ValueError(f"Invalid output template '{output_template}': unsupported placeholder or format ({error})")
+
+def build_config(overlay_type, number_of_committees, node_count, config_name, max_view=1, network='default', output_template=OUTPUT_PATH_TEMPLATE):
 
     with open(TEMPLATE_PATH, 'r') as f:
         data = json.load(f)
@@ -22,7 +43,7 @@ def build_config(overlay_type, number_of_committees, node_count, config_name, ma
     data["record_settings"] = record_update["record_settings"]
 
     data["node_count"] = int(node_count)
-    data["stream_settings"]["path"] = f"output/{config_name}.json"
+    data["stream_settings"]["path"] = output_path(output_template, overlay_type, number_of_committees, node_count, config_name, max_view, network, data["seed"])
     data["wards"][0]["max_view"] = int(max_view)
 
     if overlay_type == "tree":
@@ -40,7 +61,7 @@ def build_config(overlay_type, number_of_committees, node_count, config_name, ma
 
 if __name__ == "__main__":
     if len(sys.argv) < 5:
-        print("Usage: python config_builder.py <overlay_type> <number_of_committees> <node_count> <config_name> [max_view] [network_config]")
+        print("Usage: python config_builder.py <overlay_type> <number_of_committees> <node_count> <config_name> [max_view] [network_config] [output_template]")
         sys.exit(1)
 
     overlay_type = sys.argv[1]
@@ -49,6 +70,11 @@ if __name__ == "__main__":
     config_name = sys.argv[4]
     max_view = sys.argv[5] if len(sys.argv) > 5 else 1
     network_config = sys.argv[6] if len(sys.argv) > 6 else 'default'
+    output_template = sys.argv[7] if len(sys.argv) > 7 else OUTPUT_PATH_TEMPLATE
 
-    build_config(overlay_type, number_of_committees, node_count, config_name, max_view, network_config)
+    try:
+        build_config(overlay_type, number_of_committees, node_count, config_name, max_view, network_config, output_template)
+    except ValueError as error:
+        print(error)
+        sys.exit(1)
 
@@ -0,0 +1,82 @@
+import argparse
+import hashlib
+import os
+import re
+import sys
+
+MANIFEST_NAME = "checksums.sha256"
+MANIFEST_LINE = re.compile(r"^([0-9a-f]{64})  (.+)$")
+
+def file_checksum(path):
+    sha256 = hashlib.sha256()
+    with open(path, 'rb') as f:
+        for chunk in iter(lambda: f.read(1024 * 1024), b""):
+            sha256.update(chunk)
+    return sha256.hexdigest()
+
+def read_manifest(manifest_path):
+    entries = {}
+    malformed = []
+    with open(manifest_path, 'r') as manifest:
+        for line in manifest:
+            line = line.rstrip("\n")
+            if not line.strip():
+                continue
+            match = MANIFEST_LINE.match(line)
+            if match is None:
+                malformed.append(line)
+            else:
+                entries[match.group(2)] = match.group(1)
+    return entries, malformed
+
+def write_checksums(manifest_path, output_files):
+    # Paths are stored relative to the manifest, so `sha256sum -c` works from its directory
+    manifest_dir = os.path.dirname(manifest_path) or "."
+    entries = {}
+    if os.path.isfile(manifest_path):
+        entries, _ = read_manifest(manifest_path)
+
+    for output_file in output_files:
+        entries[os.path.relpath(output_file, manifest_dir)] = file_checksum(output_file)
+
+    with open(manifest_path, 'w') as manifest:
+        for relative_path in sorted(entries):
+            manifest.write(f"{entries[relative_path]}  {relative_path}\n")
+    print(f"Checksums of {len(output_files)} file(s) written to {manifest_path}")
+
+def verify_checksums(manifest_path):
+    if not os.path.isfile(manifest_path):
+        print(f"MISSING MANIFEST {manifest_path}")
+        return False
+    manifest_dir = os.path.dirname(manifest_path) or "."
+    expected, malformed = read_manifest(manifest_path)
+
+    failures = len(malformed)
+    for line in malformed:
+        print(f"MALFORMED {line}")
+    for relative_path, checksum in expected.items():
+        path = os.path.join(manifest_dir, relative_path)
+        if not os.path.isfile(path):
+            print(f"MISSING  {relative_path}")
+            failures += 1
+        elif file_checksum(path) != checksum:
+            print(f"CORRUPT  {relative_path}")
+            failures += 1
+
+    print(f"{len(expected) + len(malformed) - failures}/{len(expected) + len(malformed)} entries verified")
+    return failures == 0
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser(description="Write or verify SHA-256 checksums of simulation outputs.")
+    subparsers = parser.add_subparsers(dest="command", required=True)
+    write_parser = subparsers.add_parser("write", help="Add or update the checksums of the given files in the manifest.")
+    write_parser.add_argument("output_files", type=str, nargs="+", help="Paths to the output files.")
+    write_parser.add_argument("--manifest", type=str, default=MANIFEST_NAME, help="Path to the manifest.")
+    verify_parser = subparsers.add_parser("verify", help="Check every file listed in the manifest.")
+    verify_parser.add_argument("--manifest", type=str, default=MANIFEST_NAME, help="Path to the manifest.")
+
+    args = parser.parse_args()
+    if args.command == "write":
+        write_checksums(args.manifest, args.output_files)
+    elif not verify_checksums(args.manifest):
+        sys.exit(1)
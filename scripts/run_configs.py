@@ -1,23 +1,278 @@
 import subprocess
 import argparse
+import csv
+import hashlib
+import json
 import os
+import sys
+import shlex
+import signal
+import tempfile
+import threading
 import time
+from concurrent.futures import ThreadPoolExecutor
+from checksums import write_checksums, MANIFEST_NAME
 
-def run_simulation(command):
+running_processes = set()
+running_lock = threading.Lock()
+stopping = threading.Event()
+
+def limit_resources(command, max_memory_mb, max_cpu_seconds):
+    # Limits are applied through prlimit rather than a preexec_fn, which isn't safe to use from the worker threads
+    limits = []
+    if max_memory_mb is not None:
+        limits.append(f"--as={max_memory_mb * 1024 * 1024}")
+    if max_cpu_seconds is not None:
+        # Keep the hard limit a second above the soft one so the simulation gets SIGXCPU rather than SIGKILL
+        limits.append(f"--cpu={max_cpu_seconds}:{max_cpu_seconds + 1}")
+    return ["prlimit", *limits, "--", *command] if limits else command
+
+def exit_status(returncode, output, max_memory_mb):
+    if returncode == 0:
+        return "ok"
+    if returncode == -signal.SIGXCPU:
+        return "cpu limit"
+    # Allocation failures past the address space limit abort the process
+    if max_memory_mb is not None and returncode == -signal.SIGABRT and "memory allocation" in output:
+        return "memory limit"
+    return f"failed ({returncode})"
+
+def run_simulation(command, timeout=None, max_memory_mb=None, max_cpu_seconds=None):
     start_time = time.time()
-    process = subprocess.Popen(command, stdout=subprocess.PIPE, stderr=subprocess.STDOUT, text=True, bufsize=1, universal_newlines=True)
-    process.communicate()
+    with running_lock:
+        if stopping.is_set():
+            return "interrupted", 0.0, ""
+        process = subprocess.Popen(limit_resources(command, max_memory_mb, max_cpu_seconds), stdout=subprocess.PIPE, stderr=subprocess.STDOUT,
+                                   text=True, bufsize=1, universal_newlines=True, start_new_session=True)
+        running_processes.add(process)
+    try:
+        output, _ = process.communicate(timeout=timeout)
+        status = exit_status(process.returncode, output, max_memory_mb)
+    except subprocess.TimeoutExpired:
+        os.killpg(process.pid, signal.SIGKILL)
+        output, _ = process.communicate()
+        status = "timeout"
+    finally:
+        with running_lock:
+            running_processes.discard(process)
+    if stopping.is_set():
+        status = "interrupted"
+    return status, time.time() - start_time, output
+
+def stop_simulations():
+    # Simulations run in their own sessions, so they don't receive the terminal's SIGINT and have to be killed here
+    with running_lock:
+        stopping.set()
+        for process in running_processes:
+            try:
+                os.killpg(process.pid, signal.SIGKILL)
+            except ProcessLookupError:
+                pass
+
+def derive_seed(seed, attempt):
+    digest = hashlib.sha256(f"{seed}:{attempt}".encode()).digest()
+    return int.from_bytes(digest[:8], "little")
+
+def settings_fingerprint(config):
+    # Stable across key order and formatting, so the same resolved settings always give the same fingerprint
+    return hashlib.sha256(json.dumps(config, sort_keys=True, separators=(",", ":")).encode()).hexdigest()
+
+def merge_settings(base, overrides):
+    merged = dict(base)
+    for key, value in overrides.items():
+        if isinstance(value, dict) and isinstance(merged.get(key), dict):
+            merged[key] = merge_settings(merged[key], value)
+        else:
+            merged[key] = value
+    return merged
+
+def resolve_base(config, config_path, included=()):
+    # A config can name a base settings file, relative to itself, that it overrides field by field
+    base = config.pop("base", None)
+    if base is None:
+        return config
+    included = (*included, os.path.normpath(config_path))
+    base_path = os.path.normpath(os.path.join(os.path.dirname(config_path), base))
+    if base_path in included:
+        raise ValueError(f"circular base settings: {' -> '.join([*included, base_path])}")
+
+    with open(base_path, 'r') as f:
+        base_config = json.load(f)
+    return merge_settings(resolve_base(base_config, base_path, included), config)
+
+def write_run_config(config):
+    run_config_file = tempfile.NamedTemporaryFile('w', suffix=".json", delete=False)
+    with run_config_file:
+        json.dump(config, run_config_file, indent=4)
+    return run_config_file.name
+
+def log_failure(failure_log_path, filename, attempt, seed, status, output):
+    if failure_log_path is None:
+        return
+    with open(failure_log_path, 'a') as failure_log:
+        failure_log.write(f"=== {filename} attempt {attempt} seed {seed}: {status}\n")
+        failure_log.write("\n".join((output or "").splitlines()[-20:]) + "\n")
+
+def run_post_run_hooks(post_run_hooks, config_path, output_path):
+    for hook in post_run_hooks:
+        command = hook.replace("{config}", shlex.quote(config_path)).replace("{output}", shlex.quote(output_path or ""))
+        print(f"Running post-run hook: {command}")
+        result = subprocess.run(command, shell=True)
+        if result.returncode != 0:
+            return f"post-run hook failed ({result.returncode})"
+    return "ok"
+
+def run_on_complete_hooks(on_complete_hooks):
+    failures = []
+    for command in on_complete_hooks:
+        print(f"Running on-complete hook: {command}")
+        result = subprocess.run(command, shell=True)
+        if result.returncode != 0:
+            print(f"On-complete hook failed ({result.returncode}): {command}")
+            failures.append({"config": f"on-complete: {command}", "status": f"hook failed ({result.returncode})"})
+    return failures
+
+def run_config(configs_path, filename, retries=0, timeout=None, max_memory_mb=None, max_cpu_seconds=None, failure_log_path=None, post_run_hooks=()):
+    config_path = f"{configs_path}/{filename}"
+    with open(config_path, 'r') as f:
+        config = json.load(f)
+    # Configs with a base have to be resolved before the simulator can read them
+    rewrite_config = "base" in config
+    config = resolve_base(config, config_path)
+    configured_seed = seed = config.get("seed", 0)
+
+    # Runner-only settings are removed before the config is handed to the simulator
+    rewrite_config = rewrite_config or "post_run_hooks" in config or "metadata" in config
+    metadata = config.pop("metadata", {})
+    if not isinstance(metadata, dict):
+        raise ValueError("metadata must be an object of key/value pairs")
+    settings_hooks = config.pop("post_run_hooks", [])
+    if not isinstance(settings_hooks, list) or not all(isinstance(hook, str) for hook in settings_hooks):
+        raise ValueError("post_run_hooks must be a list of shell commands")
+    post_run_hooks = [*settings_hooks, *post_run_hooks]
+
+    # Templated output paths (e.g. output/{date}/...) may point at a directory that doesn't exist yet
+    output_path = config.get("stream_settings", {}).get("path")
+    if output_path is not None and os.path.dirname(output_path):
+        os.makedirs(os.path.dirname(output_path), exist_ok=True)
+
+    for attempt in range(1, retries + 2):
+        if attempt > 1:
+            # Retry with a fresh seed derived from the original one, so a failure caused by a particular seed doesn't repeat
+            seed = config["seed"] = derive_seed(configured_seed, attempt)
+        input_settings = write_run_config(config) if rewrite_config or attempt > 1 else config_path
+        fingerprint = settings_fingerprint(config)
+
+        print(f"Starting {filename} (attempt {attempt}, seed {seed})")
+        command = ["simulation", "--input-settings", input_settings, "--stream-type", "naive"]
+        try:
+            status, duration, output = run_simulation(command, timeout, max_memory_mb, max_cpu_seconds)
+        finally:
+            if input_settings != config_path:
+                os.remove(input_settings)
+        print(f"Finished {filename}: {status} in {duration:.1f}s")
+
+        if status in ("ok", "interrupted"):
+            break
+        log_failure(failure_log_path, filename, attempt, seed, status, output)
+
+    if status == "ok":
+        status = run_post_run_hooks(post_run_hooks, config_path, output_path)
+    return {"config": filename, "status": status, "attempts": attempt, "seed": seed, "duration_s": round(duration, 1), "output": output_path,
+            "settings_fingerprint": fingerprint, "metadata": json.dumps(metadata, sort_keys=True) if metadata else None}
+
+def run_config_safely(configs_path, filename, *args):
+    # A broken config (invalid JSON, missing fields) is reported as a failed run instead of aborting the whole sweep
+    try:
+        return run_config(configs_path, filename, *args)
+    except Exception as error:
+        print(f"Failed {filename}: {error!r}")
+        return {"config": filename, "status": f"failed ({error!r})", "attempts": 0, "seed": None, "duration_s": 0.0, "output": None, "settings_fingerprint": None, "metadata": None}
+
+def print_report(results, hook_failures=()):
+    print("\nStatus report:")
+    for result in results:
+        print(f"  {result['config']}: {result['status']} after {result['attempts']} attempt(s) with seed {result['seed']}, {result['duration_s']}s, settings {(result['settings_fingerprint'] or '-')[:12]}")
+        if result["metadata"] is not None:
+            print(f"    metadata: {result['metadata']}")
+    for failure in hook_failures:
+        print(f"  {failure['config']}: {failure['status']}")
+    succeeded = sum(1 for result in results if result["status"] == "ok")
+    print(f"{succeeded}/{len(results)} simulations succeeded")
+    if hook_failures:
+        print(f"{len(hook_failures)} on-complete hook(s) failed")
+
+def write_report(results, report_path, hook_failures=()):
+    with open(report_path, 'w', newline='') as report_file:
+        writer = csv.DictWriter(report_file, fieldnames=["config", "status", "attempts", "seed", "duration_s", "output", "settings_fingerprint", "metadata"])
+        writer.writeheader()
+        writer.writerows(results)
+        writer.writerows(hook_failures)
+
+def run_simulations(configs_path, jobs=1, retries=0, timeout=None, max_memory_mb=None, max_cpu_seconds=None, report_path=None, failure_log_path=None, post_run_hooks=(), on_complete_hooks=(), checksums_path=MANIFEST_NAME):
+    filenames = sorted(filename for filename in os.listdir(configs_path) if os.path.isfile(os.path.join(configs_path, filename)))
+
+    with ThreadPoolExecutor(max_workers=jobs) as executor:
+        futures = [executor.submit(run_config_safely, configs_path, filename, retries, timeout, max_memory_mb, max_cpu_seconds, failure_log_path, post_run_hooks)
+                   for filename in filenames]
+        try:
+            results = [future.result() for future in futures]
+        except KeyboardInterrupt:
+            print("Interrupted, stopping running simulations")
+            executor.shutdown(wait=False, cancel_futures=True)
+            stop_simulations()
+            raise
+
+    hook_failures = run_on_complete_hooks(on_complete_hooks)
+
+    # Checksums are written once all runs and hooks are done, and only cover the outputs of successful runs
+    output_files = [result["output"] for result in results if result["status"] == "ok" and result["output"] is not None]
+    missing_files = [output_file for output_file in output_files if not os.path.isfile(output_file)]
+    for output_file in missing_files:
+        print(f"Output {output_file} not found, skipping its checksum")
+    if checksums_path is not None:
+        write_checksums(checksums_path, [output_file for output_file in output_files if output_file not in missing_files])
+
+    print_report(results, hook_failures)
+    if report_path is not None:
+        write_report(results, report_path, hook_failures)
+    return results, hook_failures
+
+def positive_int(value):
+    number = int(value)
+    if number < 1:
+        raise argparse.ArgumentTypeError(f"expected a positive integer, got {value}")
+    return number
 
-def run_simulations(configs_path):
-    for filename in os.listdir(configs_path):
-        if os.path.isfile(os.path.join(configs_path, filename)):
-            print(f"Starting {filename}")
-            run_simulation(["simulation", "--input-settings", f"{configs_path}/{filename}", "--stream-type", "naive"])
-            print(f"Finished {filename}")
+def non_negative_int(value):
+    number = int(value)
+    if number < 0:
+        raise argparse.ArgumentTypeError(f"expected a non-negative integer, got {value}")
+    return number
 
 if __name__ == "__main__":
     parser = argparse.ArgumentParser(description="Run simulations for all configs in the provided directory")
-    parser.add_argument("configs_path", type=str, help="The string to search for in the command's output.")
+    parser.add_argument("configs_path", type=str, help="Path to the directory containing the config files.")
+    parser.add_argument("--jobs", type=positive_int, default=1, help="Number of simulations to run concurrently.")
+    parser.add_argument("--retries", type=non_negative_int, default=0, help="Number of times a failed simulation is retried, each time with a fresh seed derived from the configured one.")
+    parser.add_argument("--timeout", type=positive_int, default=None, help="Wall-clock time limit per simulation, in seconds.")
+    parser.add_argument("--max-memory", type=positive_int, default=None,
+                        help="Address space (virtual memory) limit per simulation, in MB. This is not a limit on resident memory.")
+    parser.add_argument("--max-cpu", type=positive_int, default=None, help="CPU time limit per simulation, in seconds.")
+    parser.add_argument("--report", type=str, default=None, help="Optional path of a CSV file to write the status report to.")
+    parser.add_argument("--failure-log", type=str, default=None, help="Optional path of a file to append the output of failed attempts to.")
+    parser.add_argument("--post-run", type=str, action="append", default=[],
+                        help="Shell command run after each successful simulation, with {config} and {output} replaced by the config and output paths. "
+                             "Runs after the config's own post_run_hooks. Can be repeated.")
+    parser.add_argument("--checksums", type=str, default=MANIFEST_NAME,
+                        help="Path of the manifest the checksums of successful outputs are added to once all simulations have finished.")
+    parser.add_argument("--on-complete", type=str, action="append", default=[],
+                        help="Shell command run once after all simulations have finished. Can be repeated.")
 
     args = parser.parse_args()
-    run_simulations(args.configs_path)
+    try:
+        results, hook_failures = run_simulations(args.configs_path, args.jobs, args.retries, args.timeout, args.max_memory, args.max_cpu, args.report, args.failure_log, args.post_run, args.on_complete, args.checksums)
+    except KeyboardInterrupt:
+        sys.exit(130)
+    if hook_failures or any(result["status"] != "ok" for result in results):
+        sys.exit(1)
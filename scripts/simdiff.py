@@ -0,0 +1,66 @@
+import csv
+import argparse
+import sys
+from collections import deque
+from itertools import zip_longest
+
+def print_row(prefix, header, row):
+    print(f"{prefix} " + ", ".join(f"{key}={value}" for key, value in zip(header, row)))
+
+def simdiff(left_path, right_path, context=3):
+    with open(left_path, 'r', newline='') as left_file, open(right_path, 'r', newline='') as right_file:
+        left = csv.reader(left_file)
+        right = csv.reader(right_file)
+
+        left_header = next(left, [])
+        right_header = next(right, [])
+        if left_header != right_header:
+            print("Headers differ:")
+            print(f"  {left_path}: {left_header}")
+            print(f"  {right_path}: {right_header}")
+            return False
+
+        header = left_header
+        previous = deque(maxlen=context)
+        line = 1
+        for left_row, right_row in zip_longest(left, right):
+            if left_row is None or right_row is None:
+                longer = left_path if left_row is not None else right_path
+                print(f"Outputs match up to line {line}, but {longer} has more records")
+                return False
+
+            line += 1
+            if left_row == right_row:
+                previous.append(left_row)
+                continue
+
+            record = dict(zip(header, left_row))
+            print(f"First diverging record at line {line}: step {record.get('step_id', '?')}, node {record.get('node_id', '?')}")
+            print("Differing fields:")
+            for key, left_value, right_value in zip(header, left_row, right_row):
+                if left_value != right_value:
+                    print(f"  {key}:")
+                    print(f"    {left_path}: {left_value}")
+                    print(f"    {right_path}: {right_value}")
+
+            print("Context:")
+            for row in previous:
+                print_row("   ", header, row)
+            print_row("  <", header, left_row)
+            print_row("  >", header, right_row)
+            for _, row in zip(range(context), left):
+                print_row("   ", header, row)
+            return False
+
+    print("Outputs are identical")
+    return True
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser(description="Report the first diverging record between two simulation CSV outputs.")
+    parser.add_argument("left_path", type=str, help="Path to the first CSV output.")
+    parser.add_argument("right_path", type=str, help="Path to the second CSV output.")
+    parser.add_argument("--context", type=int, default=3, help="Number of records to print around the divergence.")
+
+    args = parser.parse_args()
+    if not simdiff(args.left_path, args.right_path, args.context):
+        sys.exit(1)